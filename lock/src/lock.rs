@@ -1,7 +1,11 @@
 use core::cell::UnsafeCell;
+use core::fmt;
 use core::marker::PhantomData;
 use core::mem;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(debug_assertions)]
+use core::sync::atomic::AtomicUsize;
 
 // high level spinlock no need to care unsafe
 
@@ -30,6 +34,63 @@ pub trait RawTryLock: RawLock {
     fn try_lock(&self) -> Result<Self::Token, ()>;
 }
 
+/*
+    poisoning - mirrors std::sync's story for Mutex.
+    if a thread panics while holding the guard, the data it was
+    protecting might be left half-updated, so we mark the lock
+    poisoned and make every later acquirer deal with that explicitly
+    instead of silently handing back a guard over broken invariants.
+
+    the guard is always returned, poisoned or not - the lock is still
+    held, only the caller's trust in the data is what's in question.
+    that's why PoisonError wraps the guard instead of discarding it:
+    into_inner()/get_ref() let you recover it once you've decided the
+    data is fine to use anyway.
+*/
+
+pub type LockResult<Guard> = Result<Guard, PoisonError<Guard>>;
+pub type TryLockResult<Guard> = Result<Guard, TryLockError<Guard>>;
+
+pub struct PoisonError<T> {
+    guard: T,
+}
+
+impl<T> PoisonError<T> {
+    fn new(guard: T) -> Self {
+        Self { guard }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.guard
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.guard
+    }
+}
+
+// no T: Debug bound here - the guard inside may wrap a T that isn't
+// Debug at all, so this can't just derive. matches std::sync::PoisonError.
+impl<T> fmt::Debug for PoisonError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "PoisonError { .. }".fmt(f)
+    }
+}
+
+pub enum TryLockError<T> {
+    WouldBlock,
+    Poisoned(PoisonError<T>),
+}
+
+impl<T> fmt::Debug for TryLockError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryLockError::WouldBlock => write!(f, "WouldBlock"),
+            TryLockError::Poisoned(..) => write!(f, "Poisoned(..)"),
+        }
+    }
+}
+
 /*
     now lock and data comes in a pair
     unsafeCell - internal mutability for initial access
@@ -39,6 +100,12 @@ pub trait RawTryLock: RawLock {
 pub struct Lock<L: RawLock, T> {
     lock: L,
     data: UnsafeCell<T>,
+    // set in LockGuard::drop when a guard is dropped mid-panic
+    poisoned: AtomicBool,
+    // lazily-assigned lockdep class id, usize::MAX until first acquired;
+    // see the `lockdep` module - debug-only, never touched in release builds
+    #[cfg(debug_assertions)]
+    lockdep_class: AtomicUsize,
 }
 
 /*
@@ -68,6 +135,9 @@ impl<L: RawLock, T> Lock<L, T> {
             // for spinlock, init to false, as seen in rawlock
             lock: L::default(),
             data: UnsafeCell::new(data),
+            poisoned: AtomicBool::new(false),
+            #[cfg(debug_assertions)]
+            lockdep_class: AtomicUsize::new(usize::MAX),
         }
     }
 
@@ -76,32 +146,113 @@ impl<L: RawLock, T> Lock<L, T> {
     pub fn into_inner(self) -> T {
         self.data.into_inner()
     }
+
+    // assigns this Lock a lockdep class the first time it's needed,
+    // reusing it on every later call
+    #[cfg(debug_assertions)]
+    fn lockdep_class(&self) -> usize {
+        let existing = self.lockdep_class.load(Ordering::Relaxed);
+        if existing != usize::MAX {
+            return existing;
+        }
+        let id = crate::lockdep::next_class_id();
+        match self
+            .lockdep_class
+            .compare_exchange(usize::MAX, id, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => id,
+            // someone else assigned it first, use theirs
+            Err(actual) => actual,
+        }
+    }
+
+    // `acquire()` can panic (that's the whole point - it's how a detected
+    // ordering inversion gets reported), but by the time it runs we've
+    // already taken the raw lock and nothing owns it yet (the LockGuard
+    // that would release it on drop isn't built until after this call).
+    // unwinding straight through here would leave the raw lock held
+    // forever, so catch the panic, release what we just acquired, and
+    // resume the unwind.
+    //
+    // `site` is captured by the caller rather than via #[track_caller] on
+    // this function: it's invoked through a catch_unwind closure below, and
+    // #[track_caller] doesn't propagate through closure calls, so this
+    // would otherwise always report the closure's call site instead of the
+    // user's .lock()/.try_lock() site.
+    #[cfg(debug_assertions)]
+    fn lockdep_acquire_or_release(&self, token: &L::Token, class: usize, site: &'static core::panic::Location<'static>) {
+        if let Err(payload) =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| crate::lockdep::acquire(class, site)))
+        {
+            unsafe { self.lock.unlock(token.clone()) };
+            std::panic::resume_unwind(payload);
+        }
+    }
     /*
         calling  lock acquires inner lock and ret lockguard
         LockGuard is a proof that you acquired the lock
         
     */
-    pub fn lock(&self) -> LockGuard<L, T> {
+    // always returns the guard - poisoned or not, the lock is held either way.
+    // Err just means a previous holder panicked while holding it.
+    #[track_caller]
+    pub fn lock(&self) -> LockResult<LockGuard<L, T>> {
+        #[cfg(debug_assertions)]
+        let lockdep_class = self.lockdep_class();
+        // captured here, not inside lockdep_acquire_or_release - this
+        // function is #[track_caller] and called directly by user code, so
+        // Location::caller() here is the real .lock() call site.
+        #[cfg(debug_assertions)]
+        let site = core::panic::Location::caller();
         /*
             token partly proves that you acquired the lock,
             and shouldbe given to the lock function
         */
         let token = self.lock.lock();
-        LockGuard {
+        // only record as held once we actually hold it
+        #[cfg(debug_assertions)]
+        self.lockdep_acquire_or_release(&token, lockdep_class, site);
+        let guard = LockGuard {
             lock: self,
             token,
             _marker: PhantomData,
+            #[cfg(debug_assertions)]
+            lockdep_class,
+        };
+        if self.poisoned.load(Ordering::Acquire) {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
         }
     }
 }
 
 impl<L: RawTryLock, T> Lock<L, T> {
-    pub fn try_lock(&self) -> Result<LockGuard<L, T>, ()> {
-        self.lock.try_lock().map(|token| LockGuard {
-            lock: self,
-            token,
-            _marker: PhantomData,
-        })
+    #[track_caller]
+    pub fn try_lock(&self) -> TryLockResult<LockGuard<L, T>> {
+        #[cfg(debug_assertions)]
+        let site = core::panic::Location::caller();
+        match self.lock.try_lock() {
+            Ok(token) => {
+                #[cfg(debug_assertions)]
+                let lockdep_class = self.lockdep_class();
+                #[cfg(debug_assertions)]
+                self.lockdep_acquire_or_release(&token, lockdep_class, site);
+                let guard = LockGuard {
+                    lock: self,
+                    token,
+                    _marker: PhantomData,
+                    #[cfg(debug_assertions)]
+                    lockdep_class,
+                };
+                if self.poisoned.load(Ordering::Acquire) {
+                    Err(TryLockError::Poisoned(PoisonError::new(guard)))
+                } else {
+                    Ok(guard)
+                }
+            }
+            Err(()) => Err(TryLockError::WouldBlock),
+        }
     }
 }
 
@@ -141,6 +292,9 @@ pub struct LockGuard<'s, L: RawLock, T> {
     lock: &'s Lock<L, T>,
     token: L::Token, // token given to the lock function
     _marker: PhantomData<*const ()>, // !Send + !Sync
+    // class this guard was recorded as holding, so drop() knows what to pop
+    #[cfg(debug_assertions)]
+    lockdep_class: usize,
 }
 
 unsafe impl<'s, L: RawLock, T> Send for LockGuard<'s, L, T> {}
@@ -157,6 +311,14 @@ impl<'s, L: RawLock, T> LockGuard<'s, L, T> {
 */
 impl<'s, L: RawLock, T> Drop for LockGuard<'s, L, T> {
     fn drop(&mut self) {
+        // if we're unwinding out of this guard, the data it protects may
+        // have been left half-modified - poison the lock so the next
+        // acquirer has to opt in to seeing it
+        if std::thread::panicking() {
+            self.lock.poisoned.store(true, Ordering::Release);
+        }
+        #[cfg(debug_assertions)]
+        crate::lockdep::release(self.lockdep_class);
         // unsafe cuz unlock func is unsafe
         unsafe { self.lock.lock.unlock(self.token.clone()) };
     }
@@ -182,6 +344,14 @@ impl<'s, L: RawLock, T> DerefMut for LockGuard<'s, L, T> {
     }
 }
 
+// only available when T is, same as std's MutexGuard - print through to
+// the protected data rather than the guard's own (uninteresting) fields
+impl<'s, L: RawLock, T: fmt::Debug> fmt::Debug for LockGuard<'s, L, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 impl<'s, L: RawLock, T> LockGuard<'s, L, T> {
     pub fn into_raw(self) -> usize {
         let ret = self.lock as *const _ as usize;
@@ -189,11 +359,18 @@ impl<'s, L: RawLock, T> LockGuard<'s, L, T> {
         ret
     }
 
+    #[track_caller]
     pub unsafe fn from_raw(data: usize, token: L::Token) -> Self {
+        let lock: &'s Lock<L, T> = &*(data as *const _);
         Self {
-            lock: &*(data as *const _),
+            lock,
             token,
             _marker: PhantomData,
+            // into_raw() forgot the guard without releasing lockdep's
+            // bookkeeping, so the class is still on the thread's stack -
+            // just look it up again, it was already assigned
+            #[cfg(debug_assertions)]
+            lockdep_class: lock.lockdep_class(),
         }
     }
 }
@@ -214,14 +391,14 @@ pub mod tests {
             for i in 1..LENGTH {
                 let d = &d;
                 s.spawn(move |_| {
-                    let mut d = d.lock();
+                    let mut d = d.lock().unwrap();
                     d.push(i);
                 });
             }
         })
         .unwrap();
 
-        let mut d = d.lock();
+        let mut d = d.lock().unwrap();
         d.sort();
         assert_eq!(d.deref(), &(1..LENGTH).collect::<Vec<usize>>());
     }
@@ -0,0 +1,167 @@
+use core::panic::Location;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::cell::{RefCell, UnsafeCell};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::lock::RawLock;
+use crate::spinlock::SpinLock;
+
+/*
+    opt-in (debug-only) lock-ordering checker layered over Lock<L, T>.
+
+    every Lock lazily gets a unique "class" id the first time it's
+    acquired. per thread we keep a stack of the classes currently
+    held, and globally we remember every "class A held while
+    acquiring class B" edge any thread has ever produced. if adding
+    the new edge would close a cycle - i.e. class B already has a
+    path back to class A - some other thread could one day acquire
+    the same two locks in the opposite order and deadlock, so we
+    panic on the spot instead of waiting for that race to happen
+    under load.
+
+    all of this is cfg(debug_assertions) only; in release builds
+    Lock<L, T> never touches this module and it compiles to nothing.
+*/
+
+static NEXT_CLASS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn next_class_id() -> usize {
+    NEXT_CLASS.fetch_add(1, Ordering::Relaxed)
+}
+
+thread_local! {
+    // classes this thread currently holds, oldest first, with the
+    // call site that acquired each one
+    static HELD: RefCell<Vec<(usize, &'static Location<'static>)>> = RefCell::new(Vec::new());
+}
+
+type Edges = HashMap<usize, HashMap<usize, (&'static Location<'static>, &'static Location<'static>)>>;
+
+// guarded by its own plain SpinLock rather than our instrumented
+// Lock<L, T> - recording an edge must never itself go through
+// lockdep, or it would recurse into recording an edge.
+struct Graph {
+    lock: SpinLock,
+    edges: UnsafeCell<Edges>,
+}
+
+unsafe impl Sync for Graph {}
+
+static GRAPH: OnceLock<Graph> = OnceLock::new();
+
+fn graph() -> &'static Graph {
+    GRAPH.get_or_init(|| Graph {
+        lock: SpinLock::default(),
+        edges: UnsafeCell::new(HashMap::new()),
+    })
+}
+
+impl Graph {
+    fn with<R>(&self, f: impl FnOnce(&mut Edges) -> R) -> R {
+        let token = self.lock.lock();
+        let ret = f(unsafe { &mut *self.edges.get() });
+        unsafe { self.lock.unlock(token) };
+        ret
+    }
+}
+
+// can `target` be reached from `start` by following previously observed edges?
+fn reaches(edges: &Edges, start: usize, target: usize) -> bool {
+    let mut stack = vec![start];
+    let mut seen = HashSet::new();
+    while let Some(node) = stack.pop() {
+        if node == target {
+            return true;
+        }
+        if !seen.insert(node) {
+            continue;
+        }
+        if let Some(next) = edges.get(&node) {
+            stack.extend(next.keys().copied());
+        }
+    }
+    false
+}
+
+/// Records that `class` is being acquired at `site` while the calling thread
+/// already holds the classes on its stack, and panics if doing so would
+/// close a lock-ordering cycle.
+///
+/// `site` is taken as a parameter rather than via `#[track_caller]` here:
+/// this is always called through `Lock::lockdep_acquire_or_release`, itself
+/// reached through a `catch_unwind` closure, and `#[track_caller]` does not
+/// propagate through closure calls - the caller captures `Location::caller()`
+/// itself (inside `Lock::lock`/`try_lock`, which user code calls directly)
+/// and passes it down.
+pub fn acquire(class: usize, site: &'static Location<'static>) {
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        let mut cycle = None;
+        graph().with(|edges| {
+            for &(prior, prior_site) in held.iter() {
+                if prior == class {
+                    continue;
+                }
+                edges
+                    .entry(prior)
+                    .or_default()
+                    .entry(class)
+                    .or_insert((prior_site, site));
+                if cycle.is_none() && reaches(edges, class, prior) {
+                    cycle = Some((prior, prior_site));
+                }
+            }
+        });
+        if let Some((prior, prior_site)) = cycle {
+            panic!(
+                "lock order inversion detected: class {class} acquired at {site} while class \
+                 {prior} (acquired at {prior_site}) is already held on this thread - some other \
+                 thread acquiring these two locks in the opposite order would deadlock"
+            );
+        }
+        held.push((class, site));
+    });
+}
+
+/// Pops `class` off this thread's held stack. Called from `LockGuard::drop`.
+pub fn release(class: usize) {
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        if let Some(pos) = held.iter().rposition(|&(c, _)| c == class) {
+            held.remove(pos);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock::Lock;
+    use crate::spinlock::SpinLock;
+
+    // each Lock gets its own globally-unique class id regardless of which
+    // test created it, so these don't interfere with each other (or with
+    // any other test in the binary) even run concurrently.
+    #[test]
+    fn consistent_order_does_not_panic() {
+        let a = Lock::<SpinLock, ()>::new(());
+        let b = Lock::<SpinLock, ()>::new(());
+        for _ in 0..3 {
+            let _ga = a.lock().unwrap();
+            let _gb = b.lock().unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "lock order inversion detected")]
+    fn inconsistent_order_panics() {
+        let a = Lock::<SpinLock, ()>::new(());
+        let b = Lock::<SpinLock, ()>::new(());
+        {
+            let _ga = a.lock().unwrap();
+            let _gb = b.lock().unwrap();
+        }
+        let _gb = b.lock().unwrap();
+        let _ga = a.lock().unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use core::cell::RefCell;
+use core::hint;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use crate::lock::*;
+
+/*
+    per-acquirer queue node. each waiter spins on its own `locked`
+    flag instead of one shared flag, so contention doesn't bounce a
+    single cache line between every waiting core - the predecessor
+    that's unlocking is the only other thread that ever writes our
+    `locked` flag.
+*/
+pub struct Node {
+    locked: AtomicBool,
+    next: AtomicPtr<Node>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(true),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn reset(&mut self) {
+        *self.locked.get_mut() = true;
+        *self.next.get_mut() = ptr::null_mut();
+    }
+}
+
+/*
+    every lock()/unlock() used to Box::new/Box::from_raw a fresh node,
+    which meant hundreds of threads hammering the global allocator on
+    every single acquisition under heavy contention - that contention
+    was enough to stall whichever thread needed to run next to make
+    progress, producing a livelock that had nothing to do with the
+    queueing logic itself. each thread now keeps its own small stack of
+    already-allocated nodes and only reaches for the allocator when that
+    stack is empty (once per node a thread holds at the same time, not
+    once per acquisition); recycling them locally avoids
+    that cross-thread contention entirely.
+*/
+thread_local! {
+    // each Box's address has to stay stable once handed out as a *mut Node
+    // (other threads read/write through it via AtomicPtr), which a bare
+    // Vec<Node> can't promise across a reallocation - the boxing here isn't
+    // the redundant kind clippy::vec_box usually flags.
+    #[allow(clippy::vec_box)]
+    static NODE_POOL: RefCell<Vec<Box<Node>>> = const { RefCell::new(Vec::new()) };
+}
+
+fn take_node() -> *mut Node {
+    NODE_POOL.with(|pool| {
+        let mut node = pool.borrow_mut().pop().unwrap_or_else(|| Box::new(Node::new()));
+        node.reset();
+        Box::into_raw(node)
+    })
+}
+
+unsafe fn recycle_node(node: *mut Node) {
+    NODE_POOL.with(|pool| pool.borrow_mut().push(Box::from_raw(node)));
+}
+
+pub struct McsLock {
+    tail: AtomicPtr<Node>,
+}
+
+impl Default for McsLock {
+    fn default() -> Self {
+        Self {
+            tail: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl RawLock for McsLock {
+    // token is the node this acquirer queued with - unlock() needs it
+    // back to find/notify whoever queued up behind it
+    type Token = *mut Node;
+
+    fn lock(&self) -> *mut Node {
+        let node = take_node();
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        if !prev.is_null() {
+            // someone's ahead of us - link in behind them and wait
+            // for them to flip our flag on their way out
+            unsafe { (*prev).next.store(node, Ordering::Release) };
+            while unsafe { (*node).locked.load(Ordering::Acquire) } {
+                hint::spin_loop();
+            }
+        }
+        node
+    }
+
+    unsafe fn unlock(&self, token: *mut Node) {
+        let node = token;
+        let next = (*node).next.load(Ordering::Acquire);
+        if next.is_null() {
+            // nobody had linked in behind us yet - try to drop the
+            // queue back to empty. if we lose the race, someone's
+            // mid-way through lock(), so wait for them to finish linking.
+            if self
+                .tail
+                .compare_exchange(node, ptr::null_mut(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                recycle_node(node);
+                return;
+            }
+            let mut next = (*node).next.load(Ordering::Acquire);
+            while next.is_null() {
+                hint::spin_loop();
+                next = (*node).next.load(Ordering::Acquire);
+            }
+            (*next).locked.store(false, Ordering::Release);
+        } else {
+            (*next).locked.store(false, Ordering::Release);
+        }
+        recycle_node(node);
+    }
+}
+
+impl RawTryLock for McsLock {
+    // only succeeds when the queue is empty, i.e. the lock is free -
+    // same "don't make anyone wait" rule as the other try_locks
+    fn try_lock(&self) -> Result<*mut Node, ()> {
+        let node = take_node();
+        match self
+            .tail
+            .compare_exchange(ptr::null_mut(), node, Ordering::AcqRel, Ordering::Relaxed)
+        {
+            Ok(_) => Ok(node),
+            Err(_) => {
+                unsafe { recycle_node(node) };
+                Err(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock::Lock;
+    use crate::mcslock::McsLock;
+
+    #[test]
+    fn smoke() {
+        crate::lock::tests::smoke::<McsLock>();
+    }
+
+    // the per-call Box alloc/free this backend used to do produced a
+    // rare (~5-10% of runs) livelock under 1024-way contention - loop
+    // the contention smoke test to guard against it coming back.
+    #[test]
+    fn smoke_stress() {
+        for _ in 0..20 {
+            crate::lock::tests::smoke::<McsLock>();
+        }
+    }
+
+    #[test]
+    fn try_lock() {
+        let l = Lock::<McsLock, i32>::new(0);
+        let g = l.try_lock().unwrap();
+        assert!(l.try_lock().is_err());
+        drop(g);
+        assert!(l.try_lock().is_ok());
+    }
+}
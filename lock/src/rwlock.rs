@@ -0,0 +1,206 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/*
+    RawRwLock - sibling of RawLock for shared/exclusive access.
+    many readers can hold a ReadToken at the same time, a writer holds
+    the sole WriteToken and excludes readers and other writers alike.
+*/
+pub trait RawRwLock: Default {
+    type ReadToken: Clone;
+    type WriteToken: Clone;
+
+    fn lock_shared(&self) -> Self::ReadToken;
+
+    /// # Safety
+    ///
+    /// `unlock_shared()` should be called with the token given by the corresponding `lock_shared()`.
+    unsafe fn unlock_shared(&self, token: Self::ReadToken);
+
+    fn lock_exclusive(&self) -> Self::WriteToken;
+
+    /// # Safety
+    ///
+    /// `unlock_exclusive()` should be called with the token given by the corresponding `lock_exclusive()`.
+    unsafe fn unlock_exclusive(&self, token: Self::WriteToken);
+}
+
+pub trait RawRwTryLock: RawRwLock {
+    fn try_lock_shared(&self) -> Result<Self::ReadToken, ()>;
+
+    fn try_lock_exclusive(&self) -> Result<Self::WriteToken, ()>;
+}
+
+/*
+    same pairing as Lock<L, T> - lock and data travel together,
+    UnsafeCell for internal mutability for the write side.
+*/
+
+#[repr(C)]
+pub struct RwLock<L: RawRwLock, T> {
+    lock: L,
+    data: UnsafeCell<T>,
+}
+
+/*
+    unlike Lock<L, T>, readers can all deref the data at once, so T
+    itself has to tolerate shared access - hence the extra T: Sync
+    bound on Sync here that Lock doesn't need.
+*/
+unsafe impl<L: RawRwLock, T: Send> Send for RwLock<L, T> {}
+unsafe impl<L: RawRwLock, T: Send + Sync> Sync for RwLock<L, T> {}
+
+impl<L: RawRwLock, T> RwLock<L, T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            lock: L::default(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<L, T> {
+        let token = self.lock.lock_shared();
+        RwLockReadGuard {
+            lock: self,
+            token,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<L, T> {
+        let token = self.lock.lock_exclusive();
+        RwLockWriteGuard {
+            lock: self,
+            token,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<L: RawRwTryLock, T> RwLock<L, T> {
+    pub fn try_read(&self) -> Result<RwLockReadGuard<L, T>, ()> {
+        self.lock.try_lock_shared().map(|token| RwLockReadGuard {
+            lock: self,
+            token,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<L, T>, ()> {
+        self.lock.try_lock_exclusive().map(|token| RwLockWriteGuard {
+            lock: self,
+            token,
+            _marker: PhantomData,
+        })
+    }
+}
+
+pub struct RwLockReadGuard<'s, L: RawRwLock, T> {
+    lock: &'s RwLock<L, T>,
+    token: L::ReadToken,
+    _marker: PhantomData<*const ()>, // !Send + !Sync
+}
+
+// a read guard can be handed to another thread while siblings (or the
+// original thread) still hold their own read guard over the same T, so
+// this needs the same T: Sync bound std's RwLockReadGuard has - Send
+// alone would let a !Sync T like Cell<_> get aliased across threads.
+unsafe impl<'s, L: RawRwLock, T: Sync> Send for RwLockReadGuard<'s, L, T> {}
+unsafe impl<'s, L: RawRwLock, T: Sync> Sync for RwLockReadGuard<'s, L, T> {}
+
+impl<'s, L: RawRwLock, T> Drop for RwLockReadGuard<'s, L, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.lock.unlock_shared(self.token.clone()) };
+    }
+}
+
+impl<'s, L: RawRwLock, T> Deref for RwLockReadGuard<'s, L, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+pub struct RwLockWriteGuard<'s, L: RawRwLock, T> {
+    lock: &'s RwLock<L, T>,
+    token: L::WriteToken,
+    _marker: PhantomData<*const ()>, // !Send + !Sync
+}
+
+// exclusive access, so Send only needs T: Send, the same bound as
+// Lock<L, T>'s own Send - the T: Sync bound on Sync below is still needed since &T
+// escapes through Deref while the guard (and the &RwLock it points at)
+// is shared across threads.
+unsafe impl<'s, L: RawRwLock, T: Send> Send for RwLockWriteGuard<'s, L, T> {}
+unsafe impl<'s, L: RawRwLock, T: Send + Sync> Sync for RwLockWriteGuard<'s, L, T> {}
+
+impl<'s, L: RawRwLock, T> Drop for RwLockWriteGuard<'s, L, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.lock.unlock_exclusive(self.token.clone()) };
+    }
+}
+
+impl<'s, L: RawRwLock, T> Deref for RwLockWriteGuard<'s, L, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'s, L: RawRwLock, T> DerefMut for RwLockWriteGuard<'s, L, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use core::ops::Deref;
+
+    use crossbeam_utils::thread::scope;
+
+    use super::{RawRwLock, RwLock};
+
+    // many readers checking the invariant established by a handful of
+    // writers, so a backend is exercised under real reader/writer mix
+    // instead of just one side at a time.
+    pub fn smoke<L: RawRwLock>() {
+        const WRITERS: usize = 32;
+        const READERS: usize = 256;
+
+        let d = RwLock::<L, Vec<usize>>::new(vec![]);
+
+        scope(|s| {
+            for i in 0..WRITERS {
+                let d = &d;
+                s.spawn(move |_| {
+                    let mut w = d.write();
+                    w.push(i);
+                });
+            }
+            for _ in 0..READERS {
+                let d = &d;
+                s.spawn(move |_| {
+                    let r = d.read();
+                    assert!(r.len() <= WRITERS);
+                });
+            }
+        })
+        .unwrap();
+
+        let mut r = d.write();
+        r.sort();
+        assert_eq!(r.deref(), &(0..WRITERS).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,120 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam_utils::Backoff;
+
+use crate::rwlock::*;
+
+// top bit of the state word marks a writer as holding the lock, the
+// rest of the word is the number of readers currently holding it (and
+// is guaranteed to be 0 whenever the writer bit is set).
+const WRITER_BIT: usize = 1 << (usize::BITS - 1);
+
+/*
+    one atomic word for both reader count and writer flag, so a reader
+    and a writer never have to coordinate through two separate atomics.
+*/
+pub struct SpinRwLock {
+    state: AtomicUsize,
+}
+
+impl Default for SpinRwLock {
+    fn default() -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl RawRwLock for SpinRwLock {
+    type ReadToken = ();
+    type WriteToken = ();
+
+    // spins a CAS that only bumps the reader count while the writer
+    // bit is clear - a writer holding the lock blocks new readers, but a
+    // writer still waiting for the lock does not.
+    fn lock_shared(&self) {
+        let backoff = Backoff::new();
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+            if state & WRITER_BIT == 0
+                && self
+                    .state
+                    .compare_exchange(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return;
+            }
+            backoff.snooze();
+        }
+    }
+
+    unsafe fn unlock_shared(&self, _token: ()) {
+        self.state.fetch_sub(1, Ordering::Release);
+    }
+
+    // only succeeds out of a fully unlocked state, so it waits out
+    // both existing readers and a concurrent writer.
+    fn lock_exclusive(&self) {
+        let backoff = Backoff::new();
+        while self
+            .state
+            .compare_exchange(0, WRITER_BIT, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            backoff.snooze();
+        }
+    }
+
+    unsafe fn unlock_exclusive(&self, _token: ()) {
+        self.state.fetch_and(!WRITER_BIT, Ordering::Release);
+    }
+}
+
+impl RawRwTryLock for SpinRwLock {
+    fn try_lock_shared(&self) -> Result<(), ()> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & WRITER_BIT != 0 {
+            return Err(());
+        }
+        self.state
+            .compare_exchange(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|_| ())
+    }
+
+    fn try_lock_exclusive(&self) -> Result<(), ()> {
+        self.state
+            .compare_exchange(0, WRITER_BIT, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rwlock::RwLock;
+    use crate::spin_rwlock::SpinRwLock;
+
+    #[test]
+    fn smoke() {
+        crate::rwlock::tests::smoke::<SpinRwLock>();
+    }
+
+    #[test]
+    fn try_lock() {
+        let l = RwLock::<SpinRwLock, i32>::new(0);
+
+        let r1 = l.try_read().unwrap();
+        let r2 = l.try_read().unwrap();
+        assert!(l.try_write().is_err());
+        drop(r1);
+        drop(r2);
+
+        let w = l.try_write().unwrap();
+        assert!(l.try_read().is_err());
+        assert!(l.try_write().is_err());
+        drop(w);
+
+        assert!(l.try_read().is_ok());
+    }
+}
@@ -66,10 +66,34 @@ impl RawTryLock for SpinLock {
 
 #[cfg(test)]
 mod tests {
+    use crate::lock::{Lock, TryLockError};
     use crate::spinlock::SpinLock;
 
     #[test]
     fn smoke() {
         crate::lock::tests::smoke::<SpinLock>();
     }
+
+    // poisoning is Lock<L, T>'s own behavior, not anything backend-specific,
+    // so exercising it once against the simplest backend is enough.
+    #[test]
+    fn poison() {
+        let l = Lock::<SpinLock, i32>::new(0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = l.lock().unwrap();
+            panic!("oh no");
+        }));
+        assert!(result.is_err());
+
+        match l.lock() {
+            Err(poisoned) => assert_eq!(*poisoned.into_inner(), 0),
+            Ok(_) => panic!("lock should be poisoned after a panic while held"),
+        }
+
+        match l.try_lock() {
+            Err(TryLockError::Poisoned(poisoned)) => assert_eq!(*poisoned.into_inner(), 0),
+            other => panic!("try_lock should report Poisoned too, got {other:?}"),
+        };
+    }
 }
@@ -0,0 +1,74 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam_utils::Backoff;
+
+use crate::lock::*;
+
+/*
+    FIFO instead of "whoever wins the CAS": every lock() pulls a
+    ticket off next_ticket, then spins until now_serving counts up to
+    it. waiters never race a CAS on a shared flag - each waiter only
+    reads the shared now_serving counter, watching for its own number
+    to come up.
+*/
+pub struct TicketLock {
+    next_ticket: AtomicUsize,
+    now_serving: AtomicUsize,
+}
+
+impl Default for TicketLock {
+    fn default() -> Self {
+        Self {
+            next_ticket: AtomicUsize::new(0),
+            now_serving: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl RawLock for TicketLock {
+    type Token = usize;
+
+    fn lock(&self) -> usize {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let backoff = Backoff::new();
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            backoff.snooze();
+        }
+        ticket
+    }
+
+    unsafe fn unlock(&self, token: usize) {
+        self.now_serving.store(token + 1, Ordering::Release);
+    }
+}
+
+impl RawTryLock for TicketLock {
+    // only grabs the ticket if doing so doesn't make anyone wait,
+    // i.e. the lock is actually free right now
+    fn try_lock(&self) -> Result<usize, ()> {
+        let serving = self.now_serving.load(Ordering::Acquire);
+        self.next_ticket
+            .compare_exchange(serving, serving + 1, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock::Lock;
+    use crate::ticketlock::TicketLock;
+
+    #[test]
+    fn smoke() {
+        crate::lock::tests::smoke::<TicketLock>();
+    }
+
+    #[test]
+    fn try_lock() {
+        let l = Lock::<TicketLock, i32>::new(0);
+        let g = l.try_lock().unwrap();
+        assert!(l.try_lock().is_err());
+        drop(g);
+        assert!(l.try_lock().is_ok());
+    }
+}